@@ -0,0 +1,252 @@
+//! URL lists and CSV files describing several captures.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use pageshot::{PageShotError, Result};
+use crate::output::numbered_output;
+
+/// Output placeholders that differ between pages, unlike e.g. `{width}`
+const PER_URL_PLACEHOLDERS: [&str; 3] = ["{host}", "{path}", "{title}"];
+
+/// A single page to capture and the file to save it to
+#[derive(Clone, Debug)]
+pub struct CaptureJob {
+    pub url: String,
+    pub output: String,
+}
+
+/// Read capture jobs from an input file.
+///
+/// Each non-empty line is either a bare URL or a `url,output` CSV row; a
+/// leading `url,output` header row and `#` comments are skipped. Bare URLs
+/// are saved to `default_output` numbered by their position in the file.
+pub fn read_jobs(path: &str, default_output: &str) -> Result<Vec<CaptureJob>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|source| PageShotError::Io { path: path.to_string(), source })?;
+
+    let jobs = parse_jobs(&contents, default_output);
+
+    if jobs.is_empty() {
+        return Err(PageShotError::InvalidOptions(format!("Input file '{}' contains no URLs", path)));
+    }
+
+    Ok(jobs)
+}
+
+/// Parse the contents of an input file, see [`read_jobs`]
+fn parse_jobs(contents: &str, default_output: &str) -> Vec<CaptureJob> {
+    let mut jobs = Vec::new();
+    let mut has_header = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if jobs.is_empty() && line.split(',').next().is_some_and(|field| unquote(field).eq_ignore_ascii_case("url")) {
+            has_header = true;
+            continue;
+        }
+
+        let (url, output) = split_row(line, has_header);
+
+        // e.g. an output without extension, which can't be told apart from the rest of a URL
+        if output.is_none() && !line.starts_with('"') {
+            if let Some((_, rest)) = line.rsplit_once(',').filter(|(_, rest)| rest.contains('/')) {
+                eprintln!(
+                    "warning: capturing '{}' as a single URL, add a url,output header row to save it to '{}' instead",
+                    url, rest
+                );
+            }
+        }
+
+        // Outputs naming the page are mostly distinct per URL, collisions are numbered when saving
+        let output = match output {
+            Some(output) if !output.is_empty() => output,
+            _ if PER_URL_PLACEHOLDERS.iter().any(|placeholder| default_output.contains(placeholder)) => {
+                default_output.to_string()
+            }
+            _ => numbered_output(default_output, jobs.len() + 1),
+        };
+
+        jobs.push(CaptureJob { url, output });
+    }
+
+    jobs
+}
+
+/// Reserve `output` for one capture, numbering it (`page-002.png`, ...) when
+/// another capture of the run already saved to the same file
+pub fn claim_output(claimed: &mut HashSet<String>, output: String) -> String {
+    std::iter::once(output.clone())
+        .chain((2..).map(|number| numbered_output(&output, number)))
+        .find(|candidate| claimed.insert(candidate.clone()))
+        .expect("only finitely many outputs are claimed")
+}
+
+/// Split a line into its URL and output.
+///
+/// A quoted URL may contain commas, with `""` standing for a quote. Below a
+/// `url,output` header, unquoted lines are split on their last comma;
+/// otherwise only when a file name follows, so commas in the query of a bare
+/// URL are kept.
+fn split_row(line: &str, has_header: bool) -> (String, Option<String>) {
+    if let Some(quoted) = line.strip_prefix('"') {
+        let mut url = String::new();
+        let mut chars = quoted.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            if c != '"' {
+                url.push(c);
+            } else if chars.next_if(|&(_, next)| next == '"').is_some() {
+                url.push('"');
+            } else {
+                let output = quoted[i + 1..].trim_start().strip_prefix(',').map(unquote);
+                return (url, output);
+            }
+        }
+
+        // An unterminated quote runs to the end of the line
+        return (url, None);
+    }
+
+    match line.rsplit_once(',') {
+        Some((url, output)) if has_header || unquote(output).is_empty() || is_file_name(&unquote(output)) => {
+            (url.trim().to_string(), Some(unquote(output)))
+        }
+        _ => (line.to_string(), None),
+    }
+}
+
+/// Whether `field` looks like an output file rather than the tail of a query
+/// string: it has an extension or contains `{...}` placeholders
+fn is_file_name(field: &str) -> bool {
+    let extension = Path::new(field).extension().and_then(|extension| extension.to_str());
+    let has_placeholder = field.find('{').is_some_and(|open| field[open..].contains('}'));
+
+    !field.contains(['=', '&'])
+        && (has_placeholder
+            || extension.is_some_and(|extension| {
+                extension.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && extension.chars().all(|c| c.is_ascii_alphanumeric())
+            }))
+}
+
+/// Trim a CSV field and strip surrounding double quotes, unescaping `""`
+fn unquote(field: &str) -> String {
+    let field = field.trim();
+
+    match field.strip_prefix('"').and_then(|f| f.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => field.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{render_output, url_host};
+
+    fn jobs(contents: &str) -> Vec<(String, String)> {
+        parse_jobs(contents, "page.png").into_iter().map(|job| (job.url, job.output)).collect()
+    }
+
+    fn job(url: &str, output: &str) -> (String, String) {
+        (url.to_string(), output.to_string())
+    }
+
+    #[test]
+    fn bare_urls_are_numbered() {
+        assert_eq!(
+            jobs("https://a.com\n\n# comment\nhttps://b.com\n"),
+            [job("https://a.com", "page-001.png"), job("https://b.com", "page-002.png")]
+        );
+    }
+
+    #[test]
+    fn bare_url_with_commas_is_not_split() {
+        assert_eq!(jobs("https://y.com/?ids=3,4"), [job("https://y.com/?ids=3,4", "page-001.png")]);
+        assert_eq!(jobs("https://y.com/?a=1,b=2.png"), [job("https://y.com/?a=1,b=2.png", "page-001.png")]);
+    }
+
+    #[test]
+    fn csv_rows_split_on_last_comma() {
+        assert_eq!(
+            jobs("url,output\nhttps://a.com,a.png\nhttps://b.com/?ids=1,2,shots/b.jpg\n"),
+            [job("https://a.com", "a.png"), job("https://b.com/?ids=1,2", "shots/b.jpg")]
+        );
+    }
+
+    #[test]
+    fn templated_outputs_are_split_off() {
+        assert_eq!(
+            jobs("https://a.com,shots/{host}.{format}\nhttps://b.com/?ids=1,2,{title}\n"),
+            [job("https://a.com", "shots/{host}.{format}"), job("https://b.com/?ids=1,2", "{title}")]
+        );
+    }
+
+    #[test]
+    fn header_rows_always_split_on_last_comma() {
+        assert_eq!(
+            jobs("url,output\nhttps://a.com,shots/a\nhttps://b.com/?ids=3,4\nhttps://c.com\n"),
+            [job("https://a.com", "shots/a"), job("https://b.com/?ids=3", "4"), job("https://c.com", "page-003.png")]
+        );
+    }
+
+    #[test]
+    fn headerless_output_without_extension_stays_in_url() {
+        assert_eq!(jobs("https://a.com,shots/a"), [job("https://a.com,shots/a", "page-001.png")]);
+    }
+
+    #[test]
+    fn quoted_fields_may_contain_commas() {
+        assert_eq!(
+            jobs("\"url\",\"output\"\n\"https://x.com/?a=1,2\",out.png\n\"https://y.com/?q=\"\"a\"\"\",\"y, final.png\"\n"),
+            [job("https://x.com/?a=1,2", "out.png"), job("https://y.com/?q=\"a\"", "y, final.png")]
+        );
+    }
+
+    #[test]
+    fn empty_output_uses_default() {
+        assert_eq!(
+            jobs("https://a.com,\n\"https://b.com\",\n\"https://c.com\""),
+            [job("https://a.com", "page-001.png"), job("https://b.com", "page-002.png"), job("https://c.com", "page-003.png")]
+        );
+    }
+
+    #[test]
+    fn per_url_template_is_not_numbered() {
+        let jobs = parse_jobs("https://a.com\nhttps://b.com", "{host}.png");
+        assert!(jobs.iter().all(|job| job.output == "{host}.png"));
+    }
+
+    #[test]
+    fn shared_template_is_numbered() {
+        let jobs = parse_jobs("https://a.com\nhttps://b.com", "shot-{width}.{format}");
+        let outputs: Vec<_> = jobs.iter().map(|job| job.output.as_str()).collect();
+        assert_eq!(outputs, ["shot-{width}-001.{format}", "shot-{width}-002.{format}"]);
+    }
+
+    #[test]
+    fn header_only_file_has_no_jobs() {
+        assert!(jobs("url,output\n").is_empty());
+    }
+
+    #[test]
+    fn same_host_outputs_are_numbered() {
+        let mut claimed = HashSet::new();
+        let outputs: Vec<_> = parse_jobs("https://example.com/a\nhttps://example.com/b\nhttps://example.org/", "shots/{host}.png")
+            .into_iter()
+            .map(|job| {
+                let output = render_output(&job.output, |name| (name == "host").then(|| url_host(&job.url))).unwrap();
+                claim_output(&mut claimed, output)
+            })
+            .collect();
+
+        assert_eq!(outputs, ["shots/example.com.png", "shots/example.com-002.png", "shots/example.org.png"]);
+    }
+}
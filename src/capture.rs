@@ -0,0 +1,481 @@
+use std::time::{Duration, Instant, SystemTime};
+use image::{ImageFormat, RgbaImage};
+use serde::Serialize;
+
+use headless_chrome::{
+    Browser,
+    LaunchOptions,
+    Tab,
+    browser::default_executable,
+    types::PrintToPdfOptions,
+    protocol::cdp::Page::{self, CaptureScreenshotFormatOption, Viewport},
+    protocol::cdp::Emulation,
+};
+
+use crate::error::{PageShotError, Result};
+use crate::options::{CaptureOptions, FixedElements, OutputFormat, PdfOptions, WaitUntil};
+use crate::tiles;
+use crate::wait::{track_page_activity, wait_for_activity, wait_for_function, wait_for_visible};
+
+/// Delay before capturing in full-page mode when no delay is given,
+/// leaving the page time to re-layout after being resized
+const DEFAULT_FULL_PAGE_DELAY: Duration = Duration::from_millis(500);
+
+/// Time Chrome may stay silent on top of the longest wait before the
+/// connection is considered dead, headless_chrome's default idle timeout
+const IDLE_MARGIN: Duration = Duration::from_secs(30);
+
+/// JavaScript expression for the scrollable height of the page
+const PAGE_HEIGHT: &str = "Math.max(document.body.scrollHeight, document.documentElement.scrollHeight)";
+
+/// Header/footer template that renders nothing
+const EMPTY_TEMPLATE: &str = "<span></span>";
+
+/// Image or PDF bytes, paginated images and pixel dimensions of a capture
+type Encoded = (Vec<u8>, Vec<Vec<u8>>, Option<Size>);
+
+/// Result of a capture: the image or PDF bytes and what is known about the page
+#[derive(Debug)]
+pub struct CaptureOutput {
+    /// Image or PDF bytes, empty for archive-only and paginated captures
+    pub data: Vec<u8>,
+    /// Images of consecutive slices of the page when paginating, empty otherwise
+    pub pages: Vec<Vec<u8>>,
+    /// Image quality used, only set for JPEG and WebP
+    pub quality: Option<u32>,
+    /// Pixel dimensions of the image, not available for PDFs and archives
+    pub dimensions: Option<Size>,
+    /// URL after redirects
+    pub final_url: String,
+    /// HTTP status of the main document, if it was loaded over HTTP
+    pub status: Option<u32>,
+    pub title: String,
+    pub captured_at: SystemTime,
+    pub timings: Timings,
+    /// Rendered HTML, if requested
+    pub html: Option<String>,
+    /// Rendered text, if requested
+    pub text: Option<String>,
+    /// Page archive, if requested
+    pub archive: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Durations of the capture phases, in milliseconds
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Timings {
+    /// From starting navigation until the page counted as navigated
+    pub navigation_ms: u128,
+    /// Readiness conditions, resizing and delay
+    pub wait_ms: u128,
+    /// Producing the outputs
+    pub capture_ms: u128,
+    pub total_ms: u128,
+}
+
+/// Launch a headless Chrome with a window of `width`x`height`.
+///
+/// Chrome is shut down when it stays silent for longer than `longest_wait`
+/// (see [`CaptureOptions::longest_wait`]) plus 30 seconds.
+pub fn launch_browser(width: u32, height: u32, longest_wait: Duration) -> Result<Browser> {
+    let path = default_executable().map_err(PageShotError::ChromeNotFound)?;
+
+    let options = LaunchOptions::default_builder()
+        .headless(true)
+        .path(Some(path))
+        .window_size(Some((width, height)))
+        .idle_browser_timeout(longest_wait + IDLE_MARGIN)
+        .build()
+        .map_err(|e| PageShotError::BrowserLaunch(anyhow::anyhow!(e)))?;
+
+    Browser::new(options).map_err(PageShotError::BrowserLaunch)
+}
+
+/// Capture the page described by `options` in a fresh tab of `browser`,
+/// closing the tab afterwards
+pub fn capture(browser: &Browser, options: &CaptureOptions) -> Result<CaptureOutput> {
+    options.validate()?;
+
+    let tab = browser.new_tab()?;
+    let result = capture_tab(&tab, options);
+
+    // Don't let long batches accumulate open pages
+    let _ = tab.close(false);
+
+    result
+}
+
+fn capture_tab(tab: &Tab, options: &CaptureOptions) -> Result<CaptureOutput> {
+    // PDF output has no screenshot format
+    let format = options.format.screenshot_format();
+
+    // PDFs always contain the whole page, laid out for paper instead of the viewport
+    let full_page = options.full_page && format.is_some();
+
+    // Stitched and paginated images are encoded here instead of by Chrome
+    let tiled = full_page && options.tiled;
+    let encode_here = tiled || options.paginate.is_some();
+
+    // Quality only applies to JPEG and WebP, WebP encoded here is lossless
+    let quality = match options.format {
+        OutputFormat::Jpeg => Some(options.quality as u32),
+        OutputFormat::Webp if !encode_here => Some(options.quality as u32),
+        _ => None,
+    };
+
+    let scale = options.scale;
+
+    let timeout = options.timeout;
+    let deadline = Instant::now() + timeout;
+
+    // Lifecycle and network events must be observed from before navigation starts
+    let activity = track_page_activity(tab)?;
+
+    let navigation_start = Instant::now();
+    let navigation_failed = |source| PageShotError::Navigation { url: options.url.clone(), source };
+    tab.navigate_to(&options.url).map_err(navigation_failed)?;
+
+    match options.wait_until {
+        Some(wait_until) => wait_for_activity(&activity, wait_until, navigation_start, deadline, timeout)?,
+        None => {
+            tab.wait_until_navigated().map_err(navigation_failed)?;
+        }
+    }
+
+    let navigated = Instant::now();
+
+    // Wait for client-rendered content before measuring or capturing anything
+    for selector in &options.wait_for {
+        wait_for_visible(tab, selector, deadline, timeout)?;
+    }
+
+    if let Some(expression) = &options.wait_for_function {
+        wait_for_function(tab, expression, options.poll_interval, deadline, timeout)?;
+    }
+
+    // Load lazy content before measuring the page, it may grow while scrolling
+    if options.scroll_through {
+        scroll_through(tab, options.scroll_pause, options.max_height, deadline)?;
+    }
+
+    // Static elements change the layout, so convert them before measuring
+    if full_page && options.fixed_elements == FixedElements::Static {
+        restyle_fixed_elements(tab, "position", "static")?;
+    }
+
+    // Determine final dimensions based on full_page flag, the page may be taller than the viewport
+    let (final_width, final_height, page_height) = if full_page {
+        // Get full page dimensions
+        let full_width = evaluate_number(
+            tab,
+            "Math.max(document.body.scrollWidth, document.documentElement.scrollWidth)"
+        )? as u32;
+
+        let full_height = evaluate_number(tab, PAGE_HEIGHT)? as u32;
+
+        // Infinite feeds would otherwise make the viewport arbitrarily tall
+        let full_height = options.max_height.map_or(full_height, |max_height| full_height.min(max_height));
+
+        // Tiled captures keep the viewport height and scroll through the page instead
+        let viewport_height = if options.tiled { options.height } else { full_height };
+
+        // Set viewport to full page dimensions
+        tab.set_bounds(headless_chrome::types::Bounds::Normal {
+            left: Some(0),
+            top: Some(0),
+            width: Some(full_width as f64),
+            height: Some(viewport_height as f64),
+        })?;
+
+        (full_width, viewport_height, full_height)
+    } else {
+        (options.width, options.height, options.height)
+    };
+
+    // Set device metrics to ensure exact viewport dimensions
+    tab.call_method(Emulation::SetDeviceMetricsOverride {
+        width: final_width,
+        height: final_height,
+        device_scale_factor: scale,
+        mobile: false,
+        scale: None,
+        screen_width: None,
+        screen_height: None,
+        position_x: None,
+        position_y: None,
+        dont_set_visible_size: None,
+        screen_orientation: None,
+        viewport: None,
+        display_feature: None,
+        device_posture: None,
+    })?;
+
+    // Resizing can trigger new requests (responsive images, lazy loading)
+    if full_page {
+        if let Some(wait_until @ (WaitUntil::NetworkIdle0 | WaitUntil::NetworkIdle2)) = options.wait_until {
+            wait_for_activity(&activity, wait_until, Instant::now(), deadline, timeout)?;
+        }
+    }
+
+    // Give the page a moment to adjust (resizing, animations) before capturing
+    let delay = options.delay.unwrap_or(if full_page { DEFAULT_FULL_PAGE_DELAY } else { Duration::ZERO });
+    if !delay.is_zero() {
+        std::thread::sleep(delay);
+    }
+
+    let ready = Instant::now();
+    let captured_at = SystemTime::now();
+    let title = tab.get_title().unwrap_or_default();
+
+    // Take the DOM and archive right before capturing so all outputs reflect the same render
+    let html = options.html
+        .then(|| evaluate_string(tab, "document.documentElement.outerHTML"))
+        .transpose()?;
+
+    let text = options.text
+        .then(|| evaluate_string(tab, "document.documentElement.innerText"))
+        .transpose()?;
+
+    let final_url = evaluate_string(tab, "window.location.href").unwrap_or_else(|_| options.url.clone());
+    let status = activity.lock().unwrap().document_status;
+
+    let archive = match options.archive {
+        Some(archive) => Some(tab.call_method(Page::CaptureSnapshot {
+            format: Some(archive.snapshot_format()),
+        })?.data),
+        None => None,
+    };
+
+    // Encode a decoded or stitched page, split into slices when paginating
+    let encode = |page: RgbaImage| -> Result<Encoded> {
+        let dimensions = Size { width: page.width(), height: page.height() };
+
+        match options.paginate {
+            Some(height) => {
+                let page_height = ((height as f64 * scale).round() as u32).max(1);
+                Ok((Vec::new(), tiles::paginate(&page, page_height, options.format, quality)?, Some(dimensions)))
+            }
+            None => Ok((tiles::encode(&page, options.format, quality)?, Vec::new(), Some(dimensions))),
+        }
+    };
+
+    let (data, pages, dimensions) = match format {
+        _ if options.archive_only => (Vec::new(), Vec::new(), None),
+        None => (tab.print_to_pdf(Some(pdf_options(&options.pdf)))?, Vec::new(), None),
+        Some(_) if tiled => {
+            let first_tile_only = options.fixed_elements == FixedElements::FirstTile;
+            encode(tiles::capture_tiles(tab, final_width, page_height, final_height, scale, first_tile_only)?)?
+        }
+        Some(format) => {
+            // Clip to the requested region or matched element once the final viewport is in place
+            let clip = match (&options.clip, &options.selector) {
+                (Some(clip), _) => Some(clip.viewport()),
+                (None, Some(selector)) => Some(element_clip(tab, selector)?),
+                (None, None) => None,
+            };
+
+            let (capture_width, capture_height) = match &clip {
+                Some(clip) => (clip.width as u32, clip.height as u32),
+                None => (final_width, final_height),
+            };
+
+            // Pages are split from a lossless screenshot and encoded afterwards
+            let screenshot_data = tab.capture_screenshot(
+                if encode_here { CaptureScreenshotFormatOption::Png } else { format },
+                quality.filter(|_| !encode_here),
+                clip,
+                true
+            )?;
+
+            // Check for empty screenshot data (indicates capture failure)
+            if screenshot_data.is_empty() {
+                return Err(PageShotError::CaptureTooLarge {
+                    width: capture_width,
+                    height: capture_height,
+                    scale,
+                });
+            }
+
+            if encode_here {
+                encode(image::load_from_memory_with_format(&screenshot_data, ImageFormat::Png)?.to_rgba8())?
+            } else {
+                let dimensions = Size {
+                    width: (capture_width as f64 * scale) as u32,
+                    height: (capture_height as f64 * scale) as u32,
+                };
+
+                (screenshot_data, Vec::new(), Some(dimensions))
+            }
+        }
+    };
+
+    let finished = Instant::now();
+
+    Ok(CaptureOutput {
+        data,
+        pages,
+        quality: quality.filter(|_| !options.archive_only),
+        dimensions,
+        final_url,
+        status,
+        title,
+        captured_at,
+        timings: Timings {
+            navigation_ms: (navigated - navigation_start).as_millis(),
+            wait_ms: (ready - navigated).as_millis(),
+            capture_ms: (finished - ready).as_millis(),
+            total_ms: (finished - navigation_start).as_millis(),
+        },
+        html,
+        text,
+        archive,
+    })
+}
+
+/// Scroll from the top to the bottom of the page one viewport at a time,
+/// pausing after each step so lazy-loaded content can appear, then return to
+/// the top.
+///
+/// Reaching the bottom (or a step that doesn't move the page, e.g. when the
+/// window itself can't scroll) only ends the scrolling if the page didn't grow
+/// during the pause, so pages that keep growing (infinite scroll) are scrolled
+/// down to `max_height`, or until `deadline`.
+fn scroll_through(tab: &Tab, pause: Duration, max_height: Option<u32>, deadline: Instant) -> Result<()> {
+    let limit = max_height.map_or(f64::INFINITY, f64::from);
+    let js_limit = max_height.map_or("Infinity".to_string(), |max_height| max_height.to_string());
+    let step = format!(
+        "(() => {{
+            const before = window.scrollY;
+            window.scrollBy({{ top: window.innerHeight, left: 0, behavior: 'instant' }});
+            const bottom = Math.min({}, {});
+            return window.scrollY === before || window.scrollY + window.innerHeight >= bottom - 1;
+        }})()",
+        PAGE_HEIGHT, js_limit
+    );
+
+    tab.evaluate("window.scrollTo({ top: 0, left: 0, behavior: 'instant' })", false)?;
+
+    loop {
+        let at_bottom = tab.evaluate(&step, false)?
+            .value
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        let height = if at_bottom { Some(evaluate_number(tab, PAGE_HEIGHT)?) } else { None };
+
+        std::thread::sleep(pause);
+
+        if Instant::now() >= deadline {
+            break;
+        }
+
+        // Content appended during the pause makes room for another step
+        if let Some(height) = height {
+            if height >= limit || evaluate_number(tab, PAGE_HEIGHT)? <= height {
+                break;
+            }
+        }
+    }
+
+    tab.evaluate("window.scrollTo({ top: 0, left: 0, behavior: 'instant' })", false)?;
+    std::thread::sleep(pause);
+
+    Ok(())
+}
+
+/// Set `property` to `value` on every `position: fixed` or `sticky` element of
+/// the page, overriding the page's own styles
+pub(crate) fn restyle_fixed_elements(tab: &Tab, property: &str, value: &str) -> Result<()> {
+    tab.evaluate(&format!(
+        "for (const element of document.querySelectorAll('*')) {{
+            const position = window.getComputedStyle(element).position;
+            if (position === 'fixed' || position === 'sticky') {{
+                element.style.setProperty('{}', '{}', 'important');
+            }}
+        }}",
+        property, value
+    ), false)?;
+
+    Ok(())
+}
+
+/// Build the `Page.printToPDF` options from the PDF layout
+fn pdf_options(pdf: &PdfOptions) -> PrintToPdfOptions {
+    let (paper_width, paper_height) = pdf.paper.dimensions();
+    let display_header_footer = pdf.header_template.is_some() || pdf.footer_template.is_some();
+
+    // Chrome prints its own default for whichever template is missing, blank it instead
+    let (header_template, footer_template) = if display_header_footer {
+        (
+            Some(pdf.header_template.clone().unwrap_or_else(|| EMPTY_TEMPLATE.to_string())),
+            Some(pdf.footer_template.clone().unwrap_or_else(|| EMPTY_TEMPLATE.to_string())),
+        )
+    } else {
+        (None, None)
+    };
+
+    PrintToPdfOptions {
+        landscape: Some(pdf.landscape),
+        display_header_footer: Some(display_header_footer),
+        header_template,
+        footer_template,
+        print_background: Some(pdf.print_background),
+        scale: Some(pdf.scale),
+        paper_width: Some(paper_width),
+        paper_height: Some(paper_height),
+        margin_top: pdf.margin.as_ref().map(|m| m.top),
+        margin_right: pdf.margin.as_ref().map(|m| m.right),
+        margin_bottom: pdf.margin.as_ref().map(|m| m.bottom),
+        margin_left: pdf.margin.as_ref().map(|m| m.left),
+        page_ranges: pdf.page_ranges.clone(),
+        ..Default::default()
+    }
+}
+
+/// Scroll the first element matching `selector` into view and return its
+/// border box in page coordinates, ready to be used as a screenshot clip.
+///
+/// The clip keeps a scale of 1.0: the device scale factor is applied on top
+/// of it by Chrome.
+fn element_clip(tab: &Tab, selector: &str) -> Result<Viewport> {
+    let element = tab.find_element(selector)
+        .map_err(|_| PageShotError::SelectorNotFound(selector.to_string()))?;
+
+    element.scroll_into_view()?;
+
+    // Box model quads are relative to the viewport, clips are relative to the page
+    let mut clip = element.get_box_model()?.border_viewport();
+    clip.x += evaluate_number(tab, "window.scrollX")?;
+    clip.y += evaluate_number(tab, "window.scrollY")?;
+
+    if clip.width <= 0.0 || clip.height <= 0.0 {
+        return Err(PageShotError::EmptyElement {
+            selector: selector.to_string(),
+            width: clip.width,
+            height: clip.height,
+        });
+    }
+
+    Ok(clip)
+}
+
+/// Evaluate a JavaScript expression that is expected to produce a string
+fn evaluate_string(tab: &Tab, expression: &str) -> Result<String> {
+    tab.evaluate(expression, false)?
+        .value
+        .and_then(|v| v.as_str().map(str::to_string))
+        .ok_or_else(|| PageShotError::Evaluation { expression: expression.to_string(), expected: "string" })
+}
+
+/// Evaluate a JavaScript expression that is expected to produce a number
+pub(crate) fn evaluate_number(tab: &Tab, expression: &str) -> Result<f64> {
+    tab.evaluate(expression, false)?
+        .value
+        .and_then(|v| v.as_f64())
+        .ok_or_else(|| PageShotError::Evaluation { expression: expression.to_string(), expected: "number" })
+}
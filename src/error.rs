@@ -0,0 +1,129 @@
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// Result of PageShot operations
+pub type Result<T, E = PageShotError> = std::result::Result<T, E>;
+
+/// Everything that can make a capture fail.
+///
+/// Each variant maps to a stable process exit code of the CLI, see
+/// [`PageShotError::exit_code`].
+#[derive(Debug)]
+pub enum PageShotError {
+    /// The options are out of range or contradict each other
+    InvalidOptions(String),
+    /// A readiness condition (e.g. a `wait_for` selector) did not hold before the timeout elapsed
+    Timeout { condition: String, timeout: Duration },
+    /// No Chrome or Chromium executable could be found
+    ChromeNotFound(String),
+    /// Chrome was found but could not be started
+    BrowserLaunch(anyhow::Error),
+    /// The page could not be loaded
+    Navigation { url: String, source: anyhow::Error },
+    /// A JavaScript expression did not produce a value of the expected type
+    Evaluation { expression: String, expected: &'static str },
+    /// No element matches a selector
+    SelectorNotFound(String),
+    /// The element matching a selector has no visible size
+    EmptyElement { selector: String, width: f64, height: f64 },
+    /// Chrome returned no image data, which happens when the capture is too large
+    CaptureTooLarge { width: u32, height: u32, scale: f64 },
+    /// Reading or writing a file failed
+    Io { path: String, source: io::Error },
+    /// Any other failure while talking to Chrome
+    Chrome(anyhow::Error),
+    /// Decoding, stitching or encoding a captured image failed
+    Image(image::ImageError),
+}
+
+impl PageShotError {
+    /// Process exit code of the CLI for this error
+    ///
+    /// | Code | Error |
+    /// |------|-------|
+    /// | 1 | [`Io`](Self::Io) (and failures outside of PageShot) |
+    /// | 2 | [`InvalidOptions`](Self::InvalidOptions) |
+    /// | 3 | [`Timeout`](Self::Timeout) |
+    /// | 4 | [`ChromeNotFound`](Self::ChromeNotFound) |
+    /// | 5 | [`BrowserLaunch`](Self::BrowserLaunch) |
+    /// | 6 | [`Navigation`](Self::Navigation) |
+    /// | 7 | [`Evaluation`](Self::Evaluation) |
+    /// | 8 | [`SelectorNotFound`](Self::SelectorNotFound), [`EmptyElement`](Self::EmptyElement) |
+    /// | 9 | [`CaptureTooLarge`](Self::CaptureTooLarge) |
+    /// | 10 | [`Chrome`](Self::Chrome) |
+    /// | 11 | [`Image`](Self::Image) |
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            PageShotError::Io { .. } => 1,
+            PageShotError::InvalidOptions(_) => 2,
+            PageShotError::Timeout { .. } => 3,
+            PageShotError::ChromeNotFound(_) => 4,
+            PageShotError::BrowserLaunch(_) => 5,
+            PageShotError::Navigation { .. } => 6,
+            PageShotError::Evaluation { .. } => 7,
+            PageShotError::SelectorNotFound(_) | PageShotError::EmptyElement { .. } => 8,
+            PageShotError::CaptureTooLarge { .. } => 9,
+            PageShotError::Chrome(_) => 10,
+            PageShotError::Image(_) => 11,
+        }
+    }
+}
+
+impl fmt::Display for PageShotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageShotError::InvalidOptions(message) => write!(f, "{}", message),
+            PageShotError::Timeout { condition, timeout } => {
+                write!(f, "Timed out after {}ms waiting for {}", timeout.as_millis(), condition)
+            }
+            PageShotError::ChromeNotFound(reason) => write!(f, "Chrome not found: {}", reason),
+            PageShotError::BrowserLaunch(_) => write!(f, "Failed to launch Chrome"),
+            PageShotError::Navigation { url, .. } => write!(f, "Failed to navigate to {}", url),
+            PageShotError::Evaluation { expression, expected } => {
+                write!(f, "Failed to evaluate '{}' as a {}", expression, expected)
+            }
+            PageShotError::SelectorNotFound(selector) => write!(f, "No element matches selector: {}", selector),
+            PageShotError::EmptyElement { selector, width, height } => write!(
+                f,
+                "Element matching selector '{}' has no visible size ({}x{})",
+                selector, width, height
+            ),
+            PageShotError::CaptureTooLarge { width, height, scale } => write!(
+                f,
+                "Screenshot capture failed (empty data). This may happen with very large dimensions. \
+                 Try reducing scale factor or viewport size, or capture in tiles. Current: {}x{} at {}x scale = {}x{} pixels",
+                width, height, scale,
+                (*width as f64 * scale) as u32,
+                (*height as f64 * scale) as u32
+            ),
+            PageShotError::Io { path, .. } => write!(f, "Failed to access '{}'", path),
+            PageShotError::Chrome(error) => write!(f, "{}", error),
+            PageShotError::Image(_) => write!(f, "Failed to process the captured image"),
+        }
+    }
+}
+
+impl std::error::Error for PageShotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PageShotError::BrowserLaunch(source) | PageShotError::Navigation { source, .. } => Some(source.as_ref()),
+            PageShotError::Io { source, .. } => Some(source),
+            PageShotError::Image(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Errors from headless_chrome that aren't classified more precisely
+impl From<anyhow::Error> for PageShotError {
+    fn from(error: anyhow::Error) -> Self {
+        PageShotError::Chrome(error)
+    }
+}
+
+impl From<image::ImageError> for PageShotError {
+    fn from(error: image::ImageError) -> Self {
+        PageShotError::Image(error)
+    }
+}
@@ -0,0 +1,28 @@
+//! Capture screenshots, PDFs and archives of web pages with headless Chrome.
+//!
+//! ```no_run
+//! use pageshot::{CaptureOptions, OutputFormat};
+//!
+//! let options = CaptureOptions::builder("https://example.com")
+//!     .format(OutputFormat::Jpeg)
+//!     .full_page(true)
+//!     .build()?;
+//!
+//! let browser = pageshot::launch_browser(1920, 1080, options.longest_wait())?;
+//! let output = pageshot::capture(&browser, &options)?;
+//! std::fs::write("example.jpeg", &output.data)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod capture;
+mod error;
+mod options;
+mod tiles;
+mod wait;
+
+pub use capture::{capture, launch_browser, CaptureOutput, Size, Timings};
+pub use error::{PageShotError, Result};
+pub use options::{
+    longest_wait, ArchiveFormat, CaptureOptions, CaptureOptionsBuilder, Clip, FixedElements, Margins, OutputFormat,
+    PaperSize, PdfOptions, WaitUntil, MAX_QUALITY, MIN_VIEWPORT_SIZE, PDF_SCALE_RANGE, SCALE_RANGE,
+};
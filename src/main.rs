@@ -1,20 +1,39 @@
 use std::fs;
-use clap::Parser;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::process::ExitCode;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use clap::{CommandFactory, Parser, ValueEnum, error::ErrorKind};
 use anyhow::Result;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde::Serialize;
+use tokio::sync::Semaphore;
 
-use headless_chrome::{
-    Browser,
-    LaunchOptions,
-    protocol::cdp::Page::CaptureScreenshotFormatOption,
-    protocol::cdp::Emulation,
+use headless_chrome::Browser;
+
+use pageshot::{
+    ArchiveFormat, CaptureOptions, CaptureOutput, Clip, FixedElements, Margins, OutputFormat, PaperSize, PdfOptions,
+    PageShotError, Size, Timings, WaitUntil, MAX_QUALITY, MIN_VIEWPORT_SIZE, PDF_SCALE_RANGE, SCALE_RANGE,
 };
+use batch::{claim_output, read_jobs, CaptureJob};
+use output::{numbered_output, render_output, rfc3339, sanitize_file_name, sidecar_path, unix_timestamp, url_host, url_path};
+
+mod batch;
+mod output;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
 struct Args {
     /// URL to capture the screenshot
-    #[arg(short, long)]
-    url: String,
+    #[arg(short, long, required_unless_present = "input", conflicts_with = "input")]
+    url: Option<String>,
+
+    /// File with one URL per line, or `url,output` CSV rows, captured with a single browser
+    #[arg(short, long, value_name = "FILE")]
+    input: Option<String>,
 
     /// Width of the viewport
     #[arg(long, default_value_t = 1920)]
@@ -24,7 +43,13 @@ struct Args {
     #[arg(long, default_value_t = 1080)]
     height: u32,
 
-    /// Output file name
+    /// Number of pages to capture in parallel tabs in --input mode [default: 1]
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: Option<u16>,
+
+    /// Output file name, `-` writes to stdout, may contain {host}, {path}, {width}, {height}, {scale}, {format}, {timestamp} and {title}
+    /// (numbered per line in --input mode when the file gives no output and no per-page placeholders are used,
+    /// and numbered whenever two pages would be saved to the same file)
     #[arg(short, long, default_value = "screenshot.png")]
     output: String,
 
@@ -32,9 +57,89 @@ struct Args {
     #[arg(short, long)]
     full_page: bool,
 
-    /// Output format: png, jpeg, or webp
-    #[arg(long, default_value = "png")]
-    format: String,
+    /// Capture --full-page in viewport-sized tiles stitched together, for very tall pages at high --scale
+    #[arg(long, requires = "full_page")]
+    tiled: bool,
+
+    /// Truncate --full-page captures at this height in CSS pixels instead of the whole scrollable page
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..), requires = "full_page")]
+    max_height: Option<u32>,
+
+    /// Treatment of position: fixed/sticky elements in --full-page captures (first-tile requires --tiled)
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = FixedElements::Keep)]
+    fixed_elements: FixedElements,
+
+    /// Split the --full-page image into numbered images of this height in CSS pixels (page-001.png, page-002.png, ...)
+    #[arg(long, value_name = "HEIGHT", value_parser = clap::value_parser!(u32).range(1..), requires = "full_page")]
+    paginate: Option<u32>,
+
+    /// Output format: png, jpeg (jpg), webp, or pdf [default: from the output extension, else png]
+    #[arg(long)]
+    format: Option<String>,
+
+    /// PDF paper size
+    #[arg(long, value_enum, default_value_t = PaperSize::Letter)]
+    paper: PaperSize,
+
+    /// Print the PDF in landscape orientation
+    #[arg(long)]
+    landscape: bool,
+
+    /// PDF margins as one value or top,right,bottom,left (units: in, cm, mm, px; default in)
+    #[arg(long, value_name = "MARGIN")]
+    margin: Option<Margins>,
+
+    /// Include CSS backgrounds in the PDF
+    #[arg(long)]
+    print_background: bool,
+
+    /// PDF page ranges to print, e.g. "1-5, 8, 11-13" (default: all pages)
+    #[arg(long, value_name = "RANGES")]
+    page_ranges: Option<String>,
+
+    /// HTML file used as the PDF page header (supports pageNumber, totalPages, url, title and date classes)
+    #[arg(long, value_name = "FILE")]
+    header_template: Option<String>,
+
+    /// HTML file used as the PDF page footer (supports pageNumber, totalPages, url, title and date classes)
+    #[arg(long, value_name = "FILE")]
+    footer_template: Option<String>,
+
+    /// Also save a single-file archive of the rendered page next to the output
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    archive: Option<ArchiveFormat>,
+
+    /// Also save the rendered HTML (document.documentElement.outerHTML) next to the output
+    #[arg(long)]
+    save_html: bool,
+
+    /// Also save the rendered text (document.documentElement.innerText) next to the output
+    #[arg(long)]
+    save_text: bool,
+
+    /// Encode the image as base64 when writing to stdout (-o -)
+    #[arg(long, conflicts_with = "data_url")]
+    base64: bool,
+
+    /// Encode the image as a data: URL when writing to stdout (-o -)
+    #[arg(long)]
+    data_url: bool,
+
+    /// Print a JSON result object per capture on stdout instead of the success message
+    #[arg(long)]
+    json: bool,
+
+    /// Also save capture metadata (final URL, status, dimensions, timings, ...) to <output>.json
+    #[arg(long)]
+    metadata: bool,
+
+    /// Only save the --archive file, without the screenshot or PDF
+    #[arg(long, requires = "archive")]
+    archive_only: bool,
+
+    /// Scale of the PDF rendering (0.1-2.0)
+    #[arg(long, value_name = "SCALE", default_value_t = 1.0, value_parser = parse_pdf_scale)]
+    pdf_scale: f64,
 
     /// Quality for JPEG/WebP (0-100, higher is better quality)
     #[arg(long, default_value_t = 85)]
@@ -44,122 +149,563 @@ struct Args {
     #[arg(long, default_value_t = 1.0)]
     scale: f64,
 
+    /// CSS selector of a single element to capture (clips to its bounding box)
+    #[arg(long)]
+    selector: Option<String>,
+
+    /// Capture a fixed region given as x,y,width,height in CSS pixels
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", conflicts_with = "selector")]
+    clip: Option<Clip>,
+
+    /// Wait until an element matching this CSS selector exists and is visible (repeatable)
+    #[arg(long = "wait-for", value_name = "CSS")]
+    wait_for: Vec<String>,
+
+    /// Wait until this JavaScript expression evaluates to a truthy value (promises are awaited)
+    #[arg(long, value_name = "JS")]
+    wait_for_function: Option<String>,
+
+    /// Interval in milliseconds between evaluations of --wait-for-function
+    #[arg(long, value_name = "MS", default_value_t = 100)]
+    poll_interval: u64,
+
+    /// Scroll through the page top to bottom before capturing, to trigger lazy-loaded content
+    #[arg(long)]
+    scroll_through: bool,
+
+    /// Pause in milliseconds after each --scroll-through step
+    #[arg(long, value_name = "MS", default_value_t = 250, requires = "scroll_through")]
+    scroll_pause: u64,
+
+    /// Page readiness signal to wait for after navigation
+    #[arg(long, value_enum)]
+    wait_until: Option<WaitUntil>,
+
+    /// Extra time in milliseconds to wait right before capturing [default: 500 with --full-page, 0 otherwise]
+    #[arg(long, value_name = "MS")]
+    delay: Option<u64>,
+
+    /// Maximum time in milliseconds to wait for readiness conditions
+    #[arg(long, default_value_t = 30000)]
+    timeout: u64,
+
+    /// Fall back to the old silent fixes (unknown format -> from the output extension, clamped quality/scale/size) with warnings
+    #[arg(long)]
+    lenient: bool,
+
     /// Suppress success message
     #[arg(short, long)]
     silent: bool,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// Everything known about a finished capture, saved by `--metadata` and printed by `--json`
+#[derive(Serialize)]
+struct CaptureMetadata {
+    /// URL as requested
+    url: String,
+    /// URL after redirects
+    final_url: String,
+    /// HTTP status of the main document, if it was loaded over HTTP
+    status: Option<u32>,
+    title: String,
+    output: String,
+    /// Numbered files written by `--paginate`, in page order
+    pages: Option<Vec<String>>,
+    viewport: Size,
+    device_scale_factor: f64,
+    /// Pixel dimensions of the saved image, not available for PDFs and archives
+    dimensions: Option<Size>,
+    format: String,
+    quality: Option<u32>,
+    bytes: usize,
+    /// Capture time in RFC 3339 format (UTC)
+    captured_at: String,
+    timings: Timings,
+}
 
-    // Parse format and quality
-    let format = match args.format.to_lowercase().as_str() {
-        "jpeg" | "jpg" => CaptureScreenshotFormatOption::Jpeg,
-        "webp" => CaptureScreenshotFormatOption::Webp,
-        _ => CaptureScreenshotFormatOption::Png,
+/// `--json` line for a successful capture
+#[derive(Serialize)]
+struct JsonSuccess<'a> {
+    ok: bool,
+    #[serde(flatten)]
+    capture: &'a CaptureMetadata,
+}
+
+/// `--json` line for a failed capture
+#[derive(Serialize)]
+struct JsonFailure<'a> {
+    ok: bool,
+    url: &'a str,
+    error: JsonError,
+}
+
+#[derive(Serialize)]
+struct JsonError {
+    message: String,
+}
+
+/// `--output` value that streams the result to stdout
+const STDOUT_OUTPUT: &str = "-";
+
+/// Exit code for failures that aren't a [`PageShotError`], such as writing to stdout
+const EXIT_FAILURE: u8 = 1;
+
+/// Exit code when some pages of an `--input` batch failed and the others were saved
+const EXIT_PARTIAL_FAILURE: u8 = 12;
+
+/// Some captures of a batch failed, each was already reported on its own
+#[derive(Debug)]
+struct PartialFailure {
+    failed: usize,
+    total: usize,
+}
+
+impl fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} captures failed", self.failed, self.total)
+    }
+}
+
+impl std::error::Error for PartialFailure {}
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+
+    if let Err(message) = validate_args(&mut args) {
+        Args::command().error(ErrorKind::ValueValidation, message).exit();
+    }
+
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {:?}", error);
+
+            let code = match error.downcast_ref::<PageShotError>() {
+                Some(error) => error.exit_code(),
+                None if error.is::<PartialFailure>() => EXIT_PARTIAL_FAILURE,
+                None => EXIT_FAILURE,
+            };
+            ExitCode::from(code)
+        }
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    if args.output == STDOUT_OUTPUT {
+        if args.input.is_some() || args.json {
+            return Err(invalid("-o - cannot be combined with --input or --json, which also use stdout"));
+        }
+
+        if args.save_html || args.save_text || args.metadata || args.archive.is_some() || args.paginate.is_some() {
+            return Err(invalid("--save-html, --save-text, --metadata, --archive and --paginate need a file --output, not -"));
+        }
+    } else if args.base64 || args.data_url {
+        return Err(invalid("--base64 and --data-url only apply when writing to stdout (-o -)"));
+    }
+
+    // clap drops `requires = "input"` because --input conflicts with the --url that is present
+    if args.concurrency.is_some() && args.input.is_none() {
+        return Err(invalid("--concurrency only applies to --input batches"));
+    }
+
+    let jobs = match (&args.input, &args.url) {
+        (Some(input), _) => read_jobs(input, &args.output)?,
+        (None, Some(url)) => vec![CaptureJob { url: url.clone(), output: args.output.clone() }],
+        (None, None) => return Err(invalid("Either --url or --input is required")),
     };
 
-    // Quality only applies to JPEG and WebP, clamp to valid range
-    let quality = if matches!(format, CaptureScreenshotFormatOption::Jpeg | CaptureScreenshotFormatOption::Webp) {
-        Some(args.quality.clamp(0, 100) as u32)
-    } else {
-        None
+    // Catch placeholder typos before launching Chrome rather than after every capture
+    for job in &jobs {
+        render_output(&job.output, |name| placeholder(name, &args, job, OutputFormat::Png, ""))?;
+    }
+
+    // Every job shares the waits, Chrome must not be shut down as idle during any of them
+    let longest_wait = pageshot::longest_wait(
+        Duration::from_millis(args.timeout),
+        args.delay.map(Duration::from_millis),
+        Duration::from_millis(args.poll_interval),
+        Duration::from_millis(args.scroll_pause),
+    );
+
+    // One browser is shared by every capture, launching Chrome dominates batch runtime
+    let browser = pageshot::launch_browser(args.width, args.height, longest_wait).map_err(anyhow::Error::from);
+
+    if args.input.is_none() {
+        let job = &jobs[0];
+        let result = browser.and_then(|browser| capture_page(&browser, &args, job, &Mutex::default()));
+        report(&args, job, &result);
+
+        return result.map(|_| ());
+    }
+
+    let total = jobs.len();
+    let human_summary = !args.silent && !args.json;
+    let browser = match browser {
+        Ok(browser) => browser,
+        Err(error) => {
+            // Every page fails alike, and each still gets its own result (e.g. a --json line)
+            let failed: Result<CaptureMetadata> = Err(error);
+            jobs.iter().for_each(|job| report(&args, job, &failed));
+
+            return failed.map(|_| ());
+        }
     };
 
-    // Clamp scale factor to valid range (1.0 to 3.0)
-    let scale = args.scale.clamp(1.0, 3.0);
-
-    let mut options = LaunchOptions::default_builder()
-        .headless(true)
-        .build()
-        .map_err(|_| anyhow::anyhow!("Couldn't find appropriate Chrome binary."))?;
-
-    options.window_size = Some((args.width, args.height));
-
-    let browser = Browser::new(options)?;
-    let tab = browser.new_tab()?;
-
-    tab.navigate_to(&args.url)?
-        .wait_until_navigated()?;
-
-    // Determine final dimensions based on full_page flag
-    let (final_width, final_height) = if args.full_page {
-        // Get full page dimensions
-        let full_width = tab.evaluate(
-            "Math.max(document.body.scrollWidth, document.documentElement.scrollWidth)",
-            false
-        )?
-        .value
-        .and_then(|v| v.as_f64())
-        .ok_or_else(|| anyhow::anyhow!("Failed to get page width from JavaScript evaluation"))? as u32;
-
-        let full_height = tab.evaluate(
-            "Math.max(document.body.scrollHeight, document.documentElement.scrollHeight)",
-            false
-        )?
-        .value
-        .and_then(|v| v.as_f64())
-        .ok_or_else(|| anyhow::anyhow!("Failed to get page height from JavaScript evaluation"))? as u32;
-
-        // Set viewport to full page dimensions
-        tab.set_bounds(headless_chrome::types::Bounds::Normal {
-            left: Some(0),
-            top: Some(0),
-            width: Some(full_width as f64),
-            height: Some(full_height as f64),
-        })?;
-
-        (full_width, full_height)
-    } else {
-        (args.width, args.height)
+    let results = capture_batch(browser, Arc::new(args), jobs)?;
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+
+    if human_summary {
+        println!("Captured {} of {} pages ({} failed)", total - failed, total, failed);
+    }
+
+    if failed > 0 {
+        return Err(PartialFailure { failed, total }.into());
+    }
+
+    Ok(())
+}
+
+/// Report the outcome of one capture on stdout, as a single JSON line with `--json`
+fn report(args: &Args, job: &CaptureJob, result: &Result<CaptureMetadata>) {
+    match result {
+        _ if args.json => {
+            let line = match result {
+                Ok(capture) => serde_json::to_string(&JsonSuccess { ok: true, capture }),
+                Err(error) => serde_json::to_string(&JsonFailure {
+                    ok: false,
+                    url: &job.url,
+                    error: JsonError { message: format!("{:#}", error) },
+                }),
+            };
+
+            println!("{}", line.expect("capture results always serialize to JSON"));
+        }
+        Ok(metadata) if !args.silent && metadata.output != STDOUT_OUTPUT => {
+            match &metadata.pages {
+                Some(pages) => pages.iter().for_each(|page| println!("Screenshot saved to: {}", page)),
+                None => println!("Screenshot saved to: {}", metadata.output),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Check argument values clap cannot validate on its own.
+///
+/// With `--lenient`, invalid values are replaced by the closest valid one
+/// (unknown formats are dropped, so the output extension decides) and a
+/// warning is printed instead.
+fn validate_args(args: &mut Args) -> Result<(), String> {
+    let lenient = args.lenient;
+    let fix = |problem: String, fallback: &dyn fmt::Display| {
+        if lenient {
+            eprintln!("warning: {}, using {}", problem, fallback);
+            Ok(())
+        } else {
+            Err(problem)
+        }
+    };
+
+    if let Some(format) = &args.format {
+        if parse_format(format).is_err() {
+            fix(format!("invalid value '{}' for '--format': expected png, jpeg, webp or pdf", format), &"the output extension")?;
+            args.format = None;
+        }
+    }
+
+    if args.quality > MAX_QUALITY {
+        fix(format!("invalid value '{}' for '--quality': must be between 0 and {}", args.quality, MAX_QUALITY), &MAX_QUALITY)?;
+        args.quality = MAX_QUALITY;
+    }
+
+    if !SCALE_RANGE.contains(&args.scale) {
+        let clamped = if args.scale.is_nan() { 1.0 } else { args.scale.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end()) };
+        fix(format!(
+            "invalid value '{}' for '--scale': must be between {} and {}",
+            args.scale, SCALE_RANGE.start(), SCALE_RANGE.end()
+        ), &clamped)?;
+        args.scale = clamped;
+    }
+
+    for (name, size) in [("--width", &mut args.width), ("--height", &mut args.height)] {
+        if *size < MIN_VIEWPORT_SIZE {
+            fix(format!("invalid value '{}' for '{}': must be at least {}", size, name, MIN_VIEWPORT_SIZE), &MIN_VIEWPORT_SIZE)?;
+            *size = MIN_VIEWPORT_SIZE;
+        }
+    }
+
+    Ok(())
+}
+
+/// Capture all `jobs` across up to `--concurrency` tabs of the shared browser.
+///
+/// Failures are collected per job instead of aborting the batch.
+fn capture_batch(
+    browser: Browser,
+    args: Arc<Args>,
+    jobs: Vec<CaptureJob>,
+) -> Result<Vec<(CaptureJob, Result<CaptureMetadata>)>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    runtime.block_on(async {
+        let permits = Arc::new(Semaphore::new(args.concurrency.unwrap_or(1) as usize));
+        let claimed = Arc::new(Mutex::new(HashSet::new()));
+        let mut handles = Vec::with_capacity(jobs.len());
+
+        for job in jobs {
+            let permit = Arc::clone(&permits).acquire_owned().await?;
+            let browser = browser.clone();
+            let args = Arc::clone(&args);
+            let claimed = Arc::clone(&claimed);
+
+            // headless_chrome is blocking, so every capture gets its own blocking thread
+            handles.push(tokio::task::spawn_blocking(move || {
+                let result = capture_page(&browser, &args, &job, &claimed);
+                drop(permit);
+
+                report(&args, &job, &result);
+
+                if let Err(error) = &result {
+                    eprintln!("Failed to capture {}: {}", job.url, error);
+                }
+
+                (job, result)
+            }));
+        }
+
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            results.push(handle.await?);
+        }
+
+        Ok(results)
+    })
+}
+
+/// Capture `job` with the shared `browser` and save its outputs under a file
+/// name no other capture in `claimed` uses
+fn capture_page(
+    browser: &Browser,
+    args: &Args,
+    job: &CaptureJob,
+    claimed: &Mutex<HashSet<String>>,
+) -> Result<CaptureMetadata> {
+    let (options, format) = capture_options(args, job)?;
+    let capture = pageshot::capture(browser, &options)?;
+
+    save_capture(args, job, format, capture, claimed)
+}
+
+/// Translate the arguments into library options for `job`
+fn capture_options(args: &Args, job: &CaptureJob) -> Result<(CaptureOptions, OutputFormat)> {
+    // Without --format, the output extension decides
+    let inferred = OutputFormat::from_path(&job.output);
+    let format = match &args.format {
+        Some(format) => parse_format(format)?,
+        None => inferred.unwrap_or(OutputFormat::Png),
+    };
+
+    if let Some(inferred) = inferred.filter(|&inferred| inferred != format) {
+        return Err(invalid(format!(
+            "--format {} contradicts output '{}', whose extension implies {}",
+            format.name(), job.output, inferred.name()
+        )));
+    }
+
+    let pdf = PdfOptions {
+        paper: args.paper,
+        landscape: args.landscape,
+        margin: args.margin.clone(),
+        print_background: args.print_background,
+        page_ranges: args.page_ranges.clone(),
+        header_template: args.header_template.as_deref().map(read_template).transpose()?,
+        footer_template: args.footer_template.as_deref().map(read_template).transpose()?,
+        scale: args.pdf_scale,
+    };
+
+    let options = args.wait_for.iter()
+        .fold(CaptureOptions::builder(job.url.as_str()), |builder, selector| builder.wait_for(selector))
+        .viewport(args.width, args.height)
+        .full_page(args.full_page)
+        .tiled(args.tiled)
+        .max_height(args.max_height)
+        .fixed_elements(args.fixed_elements)
+        .paginate(args.paginate)
+        .format(format)
+        .quality(args.quality)
+        .scale(args.scale)
+        .pdf(pdf)
+        .archive(args.archive)
+        .archive_only(args.archive_only)
+        .html(args.save_html)
+        .text(args.save_text)
+        .selector(args.selector.clone())
+        .clip(args.clip)
+        .wait_for_function(args.wait_for_function.clone())
+        .poll_interval(Duration::from_millis(args.poll_interval))
+        .scroll_through(args.scroll_through)
+        .scroll_pause(Duration::from_millis(args.scroll_pause))
+        .wait_until(args.wait_until)
+        .delay(args.delay.map(Duration::from_millis))
+        .timeout(Duration::from_millis(args.timeout))
+        .build()?;
+
+    Ok((options, format))
+}
+
+/// Write the outputs of a finished capture where the arguments ask for them
+fn save_capture(
+    args: &Args,
+    job: &CaptureJob,
+    format: OutputFormat,
+    capture: CaptureOutput,
+    claimed: &Mutex<HashSet<String>>,
+) -> Result<CaptureMetadata> {
+    // Expand output placeholders now that the page (and its title) is known
+    let output = render_output(&job.output, |name| placeholder(name, args, job, format, &capture.title))?;
+
+    // Pages of one site share {host} and pages may share a title, so later ones get numbered
+    let output = match output {
+        output if output == STDOUT_OUTPUT => output,
+        output => claim_output(&mut claimed.lock().unwrap(), output),
+    };
+
+    if let Some(parent) = Path::new(&output).parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .map_err(|source| PageShotError::Io { path: parent.to_string_lossy().into_owned(), source })?;
+    }
+
+    if let Some(html) = &capture.html {
+        write_file(&sidecar_path(&output, "html"), html)?;
+    }
+
+    if let Some(text) = &capture.text {
+        write_file(&sidecar_path(&output, "txt"), text)?;
+    }
+
+    // Describe the saved file and write the --metadata sidecar for it
+    let finish = |output: String, pages: Option<Vec<String>>, format: &str, bytes: usize| {
+        let metadata = CaptureMetadata {
+            url: job.url.clone(),
+            final_url: capture.final_url.clone(),
+            status: capture.status,
+            title: capture.title.clone(),
+            output,
+            pages,
+            viewport: Size { width: args.width, height: args.height },
+            device_scale_factor: args.scale,
+            dimensions: capture.dimensions,
+            format: format.to_string(),
+            quality: capture.quality,
+            bytes,
+            captured_at: rfc3339(capture.captured_at),
+            timings: capture.timings,
+        };
+
+        if args.metadata {
+            write_file(&format!("{}.json", metadata.output), serde_json::to_string_pretty(&metadata)?)?;
+        }
+
+        Ok::<_, anyhow::Error>(metadata)
     };
 
-    // Set device metrics to ensure exact viewport dimensions
-    tab.call_method(Emulation::SetDeviceMetricsOverride {
-        width: final_width,
-        height: final_height,
-        device_scale_factor: scale,
-        mobile: false,
-        scale: None,
-        screen_width: None,
-        screen_height: None,
-        position_x: None,
-        position_y: None,
-        dont_set_visible_size: None,
-        screen_orientation: None,
-        viewport: None,
-        display_feature: None,
-        device_posture: None,
-    })?;
-
-    // Give the page a moment to adjust if we resized
-    if args.full_page {
-        std::thread::sleep(std::time::Duration::from_millis(500));
-    }
-
-    let screenshot_data = tab.capture_screenshot(
-        format,
-        quality,
-        None,
-        true
-    )?;
-
-    // Check for empty screenshot data (indicates capture failure)
-    if screenshot_data.is_empty() {
-        anyhow::bail!(
-            "Screenshot capture failed (empty data). This may happen with very large dimensions. \
-             Try reducing scale factor or viewport size. Current: {}x{} at {}x scale = {}x{} pixels",
-            final_width, final_height, scale,
-            (final_width as f64 * scale) as u32,
-            (final_height as f64 * scale) as u32
-        );
-    }
-
-    fs::write(&args.output, screenshot_data)?;
-
-    if !args.silent {
-        println!("Screenshot saved to: {}", args.output);
+    if let (Some(archive), Some(snapshot)) = (args.archive, &capture.archive) {
+        let archive_output = sidecar_path(&output, archive.extension());
+
+        write_file(&archive_output, snapshot)?;
+
+        if args.archive_only {
+            return finish(archive_output, None, archive.extension(), snapshot.len());
+        }
+    }
+
+    if args.paginate.is_some() {
+        let mut pages = Vec::with_capacity(capture.pages.len());
+
+        for (index, page) in capture.pages.iter().enumerate() {
+            let page_output = numbered_output(&output, index + 1);
+            write_file(&page_output, page)?;
+            pages.push(page_output);
+        }
+
+        let bytes = capture.pages.iter().map(Vec::len).sum();
+        return finish(output, Some(pages), format.name(), bytes);
+    }
+
+    if output == STDOUT_OUTPUT {
+        write_stdout(&capture.data, format, args)?;
+    } else {
+        write_file(&output, &capture.data)?;
+    }
+
+    finish(output, None, format.name(), capture.data.len())
+}
+
+/// Value of the output placeholder `name` for `job`, `None` if there is no such placeholder
+fn placeholder(name: &str, args: &Args, job: &CaptureJob, format: OutputFormat, title: &str) -> Option<String> {
+    match name {
+        "host" => Some(url_host(&job.url)),
+        "path" => Some(url_path(&job.url)),
+        "width" => Some(args.width.to_string()),
+        "height" => Some(args.height.to_string()),
+        "scale" => Some(args.scale.to_string()),
+        "format" => Some(format.name().to_string()),
+        "timestamp" => Some(unix_timestamp().to_string()),
+        "title" => Some(sanitize_file_name(title, "untitled")),
+        _ => None,
     }
+}
+
+/// Parse a `--format` value, case-insensitively
+fn parse_format(format: &str) -> Result<OutputFormat> {
+    OutputFormat::from_str(format, true)
+        .map_err(|_| invalid(format!("Unknown format '{}', expected png, jpeg, webp or pdf", format)))
+}
 
+/// Stream `data` to stdout, raw or encoded as requested by `--base64`/`--data-url`
+fn write_stdout(data: &[u8], format: OutputFormat, args: &Args) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+
+    if args.data_url {
+        write!(stdout, "data:{};base64,{}", format.mime_type(), BASE64_STANDARD.encode(data))?;
+    } else if args.base64 {
+        write!(stdout, "{}", BASE64_STANDARD.encode(data))?;
+    } else {
+        stdout.write_all(data)?;
+    }
+
+    stdout.flush()?;
     Ok(())
 }
+
+/// Read a PDF header or footer template file
+fn read_template(path: &str) -> Result<String> {
+    fs::read_to_string(path)
+        .map_err(|source| PageShotError::Io { path: path.to_string(), source }.into())
+}
+
+/// Write `contents` to the file at `path`
+fn write_file(path: &str, contents: impl AsRef<[u8]>) -> Result<()> {
+    fs::write(path, contents)
+        .map_err(|source| PageShotError::Io { path: path.to_string(), source }.into())
+}
+
+/// Parse a `--pdf-scale` value, Chrome accepts 0.1 to 2.0
+fn parse_pdf_scale(value: &str) -> Result<f64, String> {
+    let scale = value.parse::<f64>().map_err(|e| format!("invalid scale '{}': {}", value, e))?;
+
+    if !PDF_SCALE_RANGE.contains(&scale) {
+        return Err(format!(
+            "PDF scale must be between {:?} and {:?} (got {})",
+            PDF_SCALE_RANGE.start(), PDF_SCALE_RANGE.end(), scale
+        ));
+    }
+
+    Ok(scale)
+}
+
+/// Error for argument combinations that are rejected at run time
+fn invalid(message: impl Into<String>) -> anyhow::Error {
+    PageShotError::InvalidOptions(message.into()).into()
+}
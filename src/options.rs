@@ -0,0 +1,672 @@
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use clap::ValueEnum;
+
+use headless_chrome::protocol::cdp::Page::{self, CaptureScreenshotFormatOption, Viewport};
+
+use crate::error::{PageShotError, Result};
+
+/// Valid range of the device scale factor
+pub const SCALE_RANGE: std::ops::RangeInclusive<f64> = 1.0..=3.0;
+
+/// Highest image quality accepted by Chrome
+pub const MAX_QUALITY: u8 = 100;
+
+/// Smallest viewport width/height, a zero size would disable the viewport override
+pub const MIN_VIEWPORT_SIZE: u32 = 1;
+
+/// Valid range of the PDF rendering scale
+pub const PDF_SCALE_RANGE: std::ops::RangeInclusive<f64> = 0.1..=2.0;
+
+/// Everything that describes how a single page is captured.
+///
+/// Build it with [`CaptureOptions::builder`], which validates the combination
+/// of options, and pass it to [`capture`](crate::capture).
+#[derive(Clone, Debug)]
+pub struct CaptureOptions {
+    /// URL of the page to capture
+    pub url: String,
+    /// Viewport width in CSS pixels
+    pub width: u32,
+    /// Viewport height in CSS pixels
+    pub height: u32,
+    /// Capture the whole scrollable page instead of the viewport (images only)
+    pub full_page: bool,
+    /// Capture full pages in viewport-sized tiles stitched together, instead of
+    /// resizing the viewport to the whole page
+    pub tiled: bool,
+    /// Truncate full pages at this height in CSS pixels instead of the whole scrollable height
+    pub max_height: Option<u32>,
+    /// How `position: fixed` and `sticky` elements are treated in full-page captures
+    pub fixed_elements: FixedElements,
+    /// Split the image into consecutive images of this height in CSS pixels
+    pub paginate: Option<u32>,
+    pub format: OutputFormat,
+    /// Quality for JPEG/WebP (0-100)
+    pub quality: u8,
+    /// Device scale factor / pixel ratio
+    pub scale: f64,
+    pub pdf: PdfOptions,
+    /// Also take a single-file archive of the rendered page
+    pub archive: Option<ArchiveFormat>,
+    /// Only take the archive, without the screenshot or PDF
+    pub archive_only: bool,
+    /// Also return the rendered HTML (`document.documentElement.outerHTML`)
+    pub html: bool,
+    /// Also return the rendered text (`document.documentElement.innerText`)
+    pub text: bool,
+    /// CSS selector of a single element to capture
+    pub selector: Option<String>,
+    /// Fixed region to capture
+    pub clip: Option<Clip>,
+    /// CSS selectors of elements that must exist and be visible before capturing
+    pub wait_for: Vec<String>,
+    /// JavaScript expression that must be truthy before capturing (promises are awaited)
+    pub wait_for_function: Option<String>,
+    /// Interval between evaluations of `wait_for_function`
+    pub poll_interval: Duration,
+    /// Scroll through the page once before capturing, to trigger lazy loading
+    pub scroll_through: bool,
+    /// Pause after each scroll step of `scroll_through`
+    pub scroll_pause: Duration,
+    /// Page readiness signal to wait for after navigation, by default the navigation itself
+    pub wait_until: Option<WaitUntil>,
+    /// Extra time to wait right before capturing, by default 500 ms for full pages and none otherwise
+    pub delay: Option<Duration>,
+    /// Maximum time to wait for readiness conditions
+    pub timeout: Duration,
+}
+
+impl CaptureOptions {
+    /// Start building options for capturing `url`, with the same defaults as the CLI
+    pub fn builder(url: impl Into<String>) -> CaptureOptionsBuilder {
+        CaptureOptionsBuilder {
+            options: CaptureOptions {
+                url: url.into(),
+                width: 1920,
+                height: 1080,
+                full_page: false,
+                tiled: false,
+                max_height: None,
+                fixed_elements: FixedElements::Keep,
+                paginate: None,
+                format: OutputFormat::Png,
+                quality: 85,
+                scale: 1.0,
+                pdf: PdfOptions::default(),
+                archive: None,
+                archive_only: false,
+                html: false,
+                text: false,
+                selector: None,
+                clip: None,
+                wait_for: Vec::new(),
+                wait_for_function: None,
+                poll_interval: Duration::from_millis(100),
+                scroll_through: false,
+                scroll_pause: Duration::from_millis(250),
+                wait_until: None,
+                delay: None,
+                timeout: Duration::from_millis(30000),
+            },
+        }
+    }
+
+    /// Longest time a capture with these options may go without hearing from
+    /// Chrome, to pass to [`launch_browser`](crate::launch_browser)
+    pub fn longest_wait(&self) -> Duration {
+        longest_wait(self.timeout, self.delay, self.poll_interval, self.scroll_pause)
+    }
+
+    /// Check that the values are in range and don't contradict each other
+    pub fn validate(&self) -> Result<()> {
+        if self.quality > MAX_QUALITY {
+            return Err(PageShotError::InvalidOptions(format!(
+                "Quality must be between 0 and {} (got {})",
+                MAX_QUALITY, self.quality
+            )));
+        }
+
+        if !SCALE_RANGE.contains(&self.scale) {
+            return Err(PageShotError::InvalidOptions(format!(
+                "Scale must be between {} and {} (got {})",
+                SCALE_RANGE.start(), SCALE_RANGE.end(), self.scale
+            )));
+        }
+
+        if self.width < MIN_VIEWPORT_SIZE || self.height < MIN_VIEWPORT_SIZE {
+            return Err(PageShotError::InvalidOptions(format!(
+                "Viewport must be at least {0}x{0} (got {1}x{2})",
+                MIN_VIEWPORT_SIZE, self.width, self.height
+            )));
+        }
+
+        if !PDF_SCALE_RANGE.contains(&self.pdf.scale) {
+            return Err(PageShotError::InvalidOptions(format!(
+                "PDF scale must be between {} and {} (got {})",
+                PDF_SCALE_RANGE.start(), PDF_SCALE_RANGE.end(), self.pdf.scale
+            )));
+        }
+
+        if self.clip.is_some() && self.selector.is_some() {
+            return Err(PageShotError::InvalidOptions("A clip and a selector cannot be used together".to_string()));
+        }
+
+        if self.format == OutputFormat::Pdf && (self.clip.is_some() || self.selector.is_some()) {
+            return Err(PageShotError::InvalidOptions("A clip or selector cannot be used with PDF output".to_string()));
+        }
+
+        if self.format != OutputFormat::Pdf && (self.pdf.header_template.is_some() || self.pdf.footer_template.is_some()) {
+            return Err(PageShotError::InvalidOptions("Header and footer templates require PDF output".to_string()));
+        }
+
+        if self.tiled && (!self.full_page || self.format == OutputFormat::Pdf) {
+            return Err(PageShotError::InvalidOptions("Tiled capture requires a full-page image capture".to_string()));
+        }
+
+        if self.tiled && (self.clip.is_some() || self.selector.is_some()) {
+            return Err(PageShotError::InvalidOptions("Tiled capture cannot be combined with a clip or selector".to_string()));
+        }
+
+        if self.max_height == Some(0) {
+            return Err(PageShotError::InvalidOptions("Maximum page height must be positive".to_string()));
+        }
+
+        if self.fixed_elements == FixedElements::Static && (!self.full_page || self.format == OutputFormat::Pdf) {
+            return Err(PageShotError::InvalidOptions("Static fixed elements require a full-page image capture".to_string()));
+        }
+
+        if self.fixed_elements == FixedElements::FirstTile && !self.tiled {
+            return Err(PageShotError::InvalidOptions("Showing fixed elements in the first tile only requires tiled capture".to_string()));
+        }
+
+        if self.paginate == Some(0) {
+            return Err(PageShotError::InvalidOptions("Page height for pagination must be positive".to_string()));
+        }
+
+        if self.paginate.is_some() && self.format == OutputFormat::Pdf {
+            return Err(PageShotError::InvalidOptions("Pagination requires image output, PDFs are paginated by paper size".to_string()));
+        }
+
+        if self.archive_only && self.archive.is_none() {
+            return Err(PageShotError::InvalidOptions("Archive-only captures require an archive format".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Builder for [`CaptureOptions`]
+#[derive(Clone, Debug)]
+pub struct CaptureOptionsBuilder {
+    options: CaptureOptions,
+}
+
+impl CaptureOptionsBuilder {
+    pub fn viewport(mut self, width: u32, height: u32) -> Self {
+        self.options.width = width;
+        self.options.height = height;
+        self
+    }
+
+    pub fn full_page(mut self, full_page: bool) -> Self {
+        self.options.full_page = full_page;
+        self
+    }
+
+    pub fn tiled(mut self, tiled: bool) -> Self {
+        self.options.tiled = tiled;
+        self
+    }
+
+    pub fn max_height(mut self, max_height: Option<u32>) -> Self {
+        self.options.max_height = max_height;
+        self
+    }
+
+    pub fn fixed_elements(mut self, fixed_elements: FixedElements) -> Self {
+        self.options.fixed_elements = fixed_elements;
+        self
+    }
+
+    pub fn paginate(mut self, height: Option<u32>) -> Self {
+        self.options.paginate = height;
+        self
+    }
+
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.options.format = format;
+        self
+    }
+
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.options.quality = quality;
+        self
+    }
+
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.options.scale = scale;
+        self
+    }
+
+    pub fn pdf(mut self, pdf: PdfOptions) -> Self {
+        self.options.pdf = pdf;
+        self
+    }
+
+    pub fn archive(mut self, archive: Option<ArchiveFormat>) -> Self {
+        self.options.archive = archive;
+        self
+    }
+
+    pub fn archive_only(mut self, archive_only: bool) -> Self {
+        self.options.archive_only = archive_only;
+        self
+    }
+
+    pub fn html(mut self, html: bool) -> Self {
+        self.options.html = html;
+        self
+    }
+
+    pub fn text(mut self, text: bool) -> Self {
+        self.options.text = text;
+        self
+    }
+
+    pub fn selector(mut self, selector: Option<String>) -> Self {
+        self.options.selector = selector;
+        self
+    }
+
+    pub fn clip(mut self, clip: Option<Clip>) -> Self {
+        self.options.clip = clip;
+        self
+    }
+
+    /// Add a CSS selector that must match a visible element before capturing
+    pub fn wait_for(mut self, selector: impl Into<String>) -> Self {
+        self.options.wait_for.push(selector.into());
+        self
+    }
+
+    pub fn wait_for_function(mut self, expression: Option<String>) -> Self {
+        self.options.wait_for_function = expression;
+        self
+    }
+
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.options.poll_interval = interval;
+        self
+    }
+
+    pub fn scroll_through(mut self, scroll_through: bool) -> Self {
+        self.options.scroll_through = scroll_through;
+        self
+    }
+
+    pub fn scroll_pause(mut self, pause: Duration) -> Self {
+        self.options.scroll_pause = pause;
+        self
+    }
+
+    pub fn wait_until(mut self, wait_until: Option<WaitUntil>) -> Self {
+        self.options.wait_until = wait_until;
+        self
+    }
+
+    pub fn delay(mut self, delay: Option<Duration>) -> Self {
+        self.options.delay = delay;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    /// Validate and return the options
+    pub fn build(self) -> Result<CaptureOptions> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+/// Longest of the waits of a capture, see [`CaptureOptions::longest_wait`]
+pub fn longest_wait(timeout: Duration, delay: Option<Duration>, poll_interval: Duration, scroll_pause: Duration) -> Duration {
+    [Some(timeout), delay, Some(poll_interval), Some(scroll_pause)]
+        .into_iter()
+        .flatten()
+        .max()
+        .unwrap_or_default()
+}
+
+/// Navigation readiness strategies
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum WaitUntil {
+    /// Wait for the `load` event of the main frame
+    Load,
+    /// Wait for the `DOMContentLoaded` event of the main frame
+    #[value(name = "domcontentloaded")]
+    DomContentLoaded,
+    /// Wait for `load`, then until no requests have been in flight for 500 ms
+    #[value(name = "networkidle0")]
+    NetworkIdle0,
+    /// Wait for `load`, then until at most 2 requests have been in flight for 500 ms
+    #[value(name = "networkidle2")]
+    NetworkIdle2,
+}
+
+/// Treatment of `position: fixed` and `sticky` elements (headers, cookie
+/// banners, chat widgets) in full-page captures
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum FixedElements {
+    /// Leave them as they are
+    Keep,
+    /// Turn them into `position: static` so they scroll with the page and appear once, where they are in the document
+    Static,
+    /// Show them in the first tile only, hiding them for the rest of a tiled capture
+    FirstTile,
+}
+
+/// Image and document formats a page can be captured as
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Png,
+    #[value(alias = "jpg")]
+    Jpeg,
+    Webp,
+    Pdf,
+}
+
+impl OutputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Pdf => "application/pdf",
+        }
+    }
+
+    /// Format implied by the extension of `path`, if it is a known one
+    pub fn from_path(path: &str) -> Option<OutputFormat> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        OutputFormat::from_str(extension, true).ok()
+    }
+
+    /// Screenshot format for images, `None` for PDF output
+    pub(crate) fn screenshot_format(self) -> Option<CaptureScreenshotFormatOption> {
+        match self {
+            OutputFormat::Png => Some(CaptureScreenshotFormatOption::Png),
+            OutputFormat::Jpeg => Some(CaptureScreenshotFormatOption::Jpeg),
+            OutputFormat::Webp => Some(CaptureScreenshotFormatOption::Webp),
+            OutputFormat::Pdf => None,
+        }
+    }
+}
+
+/// Layout of PDF output
+#[derive(Clone, Debug)]
+pub struct PdfOptions {
+    pub paper: PaperSize,
+    pub landscape: bool,
+    /// Page margins, Chrome's default margins if not set
+    pub margin: Option<Margins>,
+    /// Include CSS backgrounds
+    pub print_background: bool,
+    /// Page ranges to print, e.g. "1-5, 8, 11-13" (default: all pages)
+    pub page_ranges: Option<String>,
+    /// HTML of the page header (supports pageNumber, totalPages, url, title and date classes)
+    pub header_template: Option<String>,
+    /// HTML of the page footer (supports pageNumber, totalPages, url, title and date classes)
+    pub footer_template: Option<String>,
+    /// Scale of the rendering (0.1-2.0)
+    pub scale: f64,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        PdfOptions {
+            paper: PaperSize::Letter,
+            landscape: false,
+            margin: None,
+            print_background: false,
+            page_ranges: None,
+            header_template: None,
+            footer_template: None,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Paper sizes for PDF output
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PaperSize {
+    Letter,
+    Legal,
+    Tabloid,
+    Ledger,
+    A0,
+    A1,
+    A2,
+    A3,
+    A4,
+    A5,
+    A6,
+}
+
+impl PaperSize {
+    /// Width and height in inches, portrait orientation
+    pub fn dimensions(self) -> (f64, f64) {
+        match self {
+            PaperSize::Letter => (8.5, 11.0),
+            PaperSize::Legal => (8.5, 14.0),
+            PaperSize::Tabloid => (11.0, 17.0),
+            PaperSize::Ledger => (17.0, 11.0),
+            PaperSize::A0 => (33.1, 46.8),
+            PaperSize::A1 => (23.4, 33.1),
+            PaperSize::A2 => (16.54, 23.4),
+            PaperSize::A3 => (11.7, 16.54),
+            PaperSize::A4 => (8.27, 11.7),
+            PaperSize::A5 => (5.83, 8.27),
+            PaperSize::A6 => (4.13, 5.83),
+        }
+    }
+}
+
+/// PDF page margins in inches
+#[derive(Clone, Debug)]
+pub struct Margins {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl FromStr for Margins {
+    type Err = String;
+
+    /// Parse one length for all sides, or `top,right,bottom,left`
+    fn from_str(value: &str) -> Result<Margins, String> {
+        let lengths = value
+            .split(',')
+            .map(parse_length)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match lengths[..] {
+            [all] => Ok(Margins { top: all, right: all, bottom: all, left: all }),
+            [top, right, bottom, left] => Ok(Margins { top, right, bottom, left }),
+            _ => Err(format!("expected 1 or 4 margins but got '{}'", value)),
+        }
+    }
+}
+
+/// Parse a length such as `0.5in`, `1cm`, `10mm` or `48px` into inches (bare numbers are inches)
+fn parse_length(value: &str) -> Result<f64, String> {
+    let value = value.trim();
+
+    let (number, inches_per_unit) = if let Some(n) = value.strip_suffix("in") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix("cm") {
+        (n, 1.0 / 2.54)
+    } else if let Some(n) = value.strip_suffix("mm") {
+        (n, 1.0 / 25.4)
+    } else if let Some(n) = value.strip_suffix("px") {
+        (n, 1.0 / 96.0)
+    } else {
+        (value, 1.0)
+    };
+
+    let number = number.trim().parse::<f64>()
+        .map_err(|e| format!("invalid length '{}': {}", value, e))?;
+
+    if number < 0.0 {
+        return Err(format!("length must not be negative (got '{}')", value));
+    }
+
+    Ok(number * inches_per_unit)
+}
+
+/// A page region in CSS pixels
+#[derive(Clone, Copy, Debug)]
+pub struct Clip {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Clip {
+    /// Screenshot clip with a scale of 1.0, the device scale factor is applied on top by Chrome
+    pub(crate) fn viewport(self) -> Viewport {
+        Viewport { x: self.x, y: self.y, width: self.width, height: self.height, scale: 1.0 }
+    }
+}
+
+impl FromStr for Clip {
+    type Err = String;
+
+    /// Parse `x,y,width,height` (CSS pixels)
+    fn from_str(value: &str) -> Result<Clip, String> {
+        let parts = value
+            .split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid number in clip '{}': {}", value, e))?;
+
+        let [x, y, width, height] = parts[..] else {
+            return Err(format!("expected x,y,width,height but got '{}'", value));
+        };
+
+        if x < 0.0 || y < 0.0 {
+            return Err(format!("clip origin must not be negative (got {},{})", x, y));
+        }
+
+        if width <= 0.0 || height <= 0.0 {
+            return Err(format!("clip width and height must be positive (got {}x{})", width, height));
+        }
+
+        Ok(Clip { x, y, width, height })
+    }
+}
+
+/// Page archive formats
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ArchiveFormat {
+    /// MHTML snapshot with all resources inlined
+    Mhtml,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::Mhtml => "mhtml",
+        }
+    }
+
+    pub(crate) fn snapshot_format(self) -> Page::CaptureSnapshotFormatOption {
+        match self {
+            ArchiveFormat::Mhtml => Page::CaptureSnapshotFormatOption::Mhtml,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_parses_four_numbers() {
+        let clip: Clip = " 10, 20.5 ,300,400".parse().unwrap();
+        assert_eq!((clip.x, clip.y, clip.width, clip.height), (10.0, 20.5, 300.0, 400.0));
+    }
+
+    #[test]
+    fn clip_rejects_wrong_field_count() {
+        assert!("10,20,300".parse::<Clip>().is_err());
+        assert!("10,20,300,400,500".parse::<Clip>().is_err());
+        assert!("".parse::<Clip>().is_err());
+    }
+
+    #[test]
+    fn clip_rejects_invalid_numbers() {
+        assert!("10,20,wide,400".parse::<Clip>().is_err());
+    }
+
+    #[test]
+    fn clip_rejects_negative_origin() {
+        assert!("-1,0,300,400".parse::<Clip>().is_err());
+        assert!("0,-1,300,400".parse::<Clip>().is_err());
+    }
+
+    #[test]
+    fn clip_rejects_empty_size() {
+        assert!("0,0,0,400".parse::<Clip>().is_err());
+        assert!("0,0,300,-5".parse::<Clip>().is_err());
+    }
+
+    fn assert_inches(value: &str, expected: f64) {
+        let inches = parse_length(value).unwrap();
+        assert!((inches - expected).abs() < 1e-9, "{} parsed as {} inches, expected {}", value, inches, expected);
+    }
+
+    #[test]
+    fn lengths_convert_to_inches() {
+        assert_inches("0.5", 0.5);
+        assert_inches("0.5in", 0.5);
+        assert_inches("2.54cm", 1.0);
+        assert_inches("25.4mm", 1.0);
+        assert_inches("48px", 0.5);
+        assert_inches(" 1 cm ", 1.0 / 2.54);
+    }
+
+    #[test]
+    fn lengths_reject_bad_values() {
+        assert!(parse_length("-1mm").is_err());
+        assert!(parse_length("1pt").is_err());
+        assert!(parse_length("mm").is_err());
+    }
+
+    #[test]
+    fn margins_take_one_or_four_lengths() {
+        let all: Margins = "1cm".parse().unwrap();
+        assert_eq!((all.top, all.right, all.bottom, all.left), (1.0 / 2.54, 1.0 / 2.54, 1.0 / 2.54, 1.0 / 2.54));
+
+        let sides: Margins = "1,2in,96px,0".parse().unwrap();
+        assert_eq!((sides.top, sides.right, sides.bottom, sides.left), (1.0, 2.0, 1.0, 0.0));
+
+        assert!("1,2".parse::<Margins>().is_err());
+        assert!("1,2,3,4,5".parse::<Margins>().is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Naming of output files: templates, sidecars and numbered outputs.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+use pageshot::{PageShotError, Result};
+
+/// Path of a file saved next to `output`, with its extension replaced by `extension`
+pub fn sidecar_path(output: &str, extension: &str) -> String {
+    Path::new(output)
+        .with_extension(extension)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Expand `{name}` placeholders in an output template using `value`.
+///
+/// Unknown placeholders are rejected so typos don't end up in file names.
+pub fn render_output(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| PageShotError::InvalidOptions(format!("Unclosed placeholder in output '{}'", template)))?;
+
+        let name = &rest[start + 1..end];
+        let expanded = value(name)
+            .ok_or_else(|| PageShotError::InvalidOptions(
+                format!("Unknown placeholder {{{}}} in output '{}'", name, template)
+            ))?;
+
+        output.push_str(&rest[..start]);
+        output.push_str(&expanded);
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Insert a zero-padded sequence number before the extension, e.g. `shot-007.png`
+pub fn numbered_output(output: &str, number: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("screenshot");
+
+    let file_name = match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{}-{:03}.{}", stem, number, extension),
+        None => format!("{}-{:03}", stem, number),
+    };
+
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+/// Host name of `url`, or `unknown-host` if it has none
+pub fn url_host(url: &str) -> String {
+    let host = Url::parse(url).ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_default();
+
+    sanitize_file_name(&host, "unknown-host")
+}
+
+/// Path of `url` flattened into a single file name component, `index` for `/`
+pub fn url_path(url: &str) -> String {
+    let path = Url::parse(url).ok()
+        .map(|u| u.path().to_string())
+        .unwrap_or_default();
+
+    sanitize_file_name(&path, "index")
+}
+
+/// Replace characters that are unsafe in file names with `-`, collapsing
+/// runs and trimming the ends. Falls back to `fallback` if nothing is left.
+pub fn sanitize_file_name(value: &str, fallback: &str) -> String {
+    let mut name = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if c.is_alphanumeric() || c == '.' || c == '_' {
+            name.push(c);
+        } else if !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+
+    let name: String = name.trim_matches(|c| c == '-' || c == '.').chars().take(100).collect();
+
+    if name.is_empty() {
+        fallback.to_string()
+    } else {
+        name
+    }
+}
+
+/// Format `time` as an RFC 3339 UTC timestamp with millisecond precision
+pub fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (hour, minute, second) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, hour, minute, second, since_epoch.subsec_millis()
+    )
+}
+
+/// Seconds since the Unix epoch
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: u64, millis: u64) -> String {
+        rfc3339(UNIX_EPOCH + Duration::from_secs(seconds) + Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn rfc3339_epoch() {
+        assert_eq!(at(0, 0), "1970-01-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn rfc3339_leap_days() {
+        assert_eq!(at(951_782_400, 0), "2000-02-29T00:00:00.000Z");
+        assert_eq!(at(1_709_210_096, 789), "2024-02-29T12:34:56.789Z");
+        assert_eq!(at(4_107_456_000 + 86_400, 0), "2100-03-01T00:00:00.000Z");
+    }
+
+    #[test]
+    fn rfc3339_end_of_year() {
+        assert_eq!(at(253_402_300_799, 999), "9999-12-31T23:59:59.999Z");
+    }
+
+    #[test]
+    fn rfc3339_before_epoch_is_clamped() {
+        assert_eq!(rfc3339(UNIX_EPOCH - Duration::from_secs(1)), "1970-01-01T00:00:00.000Z");
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+use image::{ImageFormat, RgbImage, RgbaImage, buffer::ConvertBuffer, imageops};
+use image::codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder};
+
+use headless_chrome::{Tab, protocol::cdp::Page::CaptureScreenshotFormatOption};
+
+use crate::capture::{evaluate_number, restyle_fixed_elements};
+use crate::error::{PageShotError, Result};
+use crate::options::OutputFormat;
+
+/// Time given to the page to repaint after scrolling to the next tile
+const TILE_SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// Capture a `width`x`height` page by scrolling through it one viewport of
+/// `tile_height` at a time and stitching the screenshots together.
+///
+/// Only one viewport is ever rendered by Chrome, so the page height is only
+/// limited by the memory of the stitched image. With `first_tile_only`, fixed
+/// and sticky elements are hidden after the first tile instead of repeating.
+pub(crate) fn capture_tiles(
+    tab: &Tab,
+    width: u32,
+    height: u32,
+    tile_height: u32,
+    scale: f64,
+    first_tile_only: bool,
+) -> Result<RgbaImage> {
+    let pixels = |css: u32| (css as f64 * scale).round() as u32;
+    let mut page = RgbaImage::new(pixels(width), pixels(height));
+
+    for y in (0..height).step_by(tile_height as usize) {
+        tab.evaluate(&format!("window.scrollTo({{ top: {}, left: 0, behavior: 'instant' }})", y), false)?;
+        std::thread::sleep(TILE_SETTLE_DELAY);
+
+        // The browser stops scrolling at the bottom, so the last tile may start above `y`
+        let scroll_y = (evaluate_number(tab, "window.scrollY")?.max(0.0) as u32).min(y);
+
+        let data = tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true)?;
+
+        if data.is_empty() {
+            return Err(PageShotError::CaptureTooLarge { width, height: tile_height, scale });
+        }
+
+        let tile = image::load_from_memory_with_format(&data, ImageFormat::Png)?.to_rgba8();
+
+        // Skip the rows above `y` that the previous tile already covered
+        let top = pixels(y - scroll_y).min(tile.height());
+        let rows = (tile.height() - top).min(page.height() - pixels(y));
+        let part = imageops::crop_imm(&tile, 0, top, tile.width().min(page.width()), rows);
+
+        imageops::replace(&mut page, &*part, 0, pixels(y) as i64);
+
+        // Hiding keeps the layout intact, unlike removing the elements
+        if first_tile_only && y == 0 {
+            restyle_fixed_elements(tab, "visibility", "hidden")?;
+        }
+    }
+
+    Ok(page)
+}
+
+/// Encode a stitched page in `format`; WebP is always lossless and ignores `quality`
+pub(crate) fn encode(page: &RgbaImage, format: OutputFormat, quality: Option<u32>) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    match format {
+        OutputFormat::Png => page.write_with_encoder(PngEncoder::new(&mut data))?,
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let page: RgbImage = page.convert();
+            page.write_with_encoder(JpegEncoder::new_with_quality(&mut data, quality.unwrap_or(85) as u8))?;
+        }
+        OutputFormat::Webp => page.write_with_encoder(WebPEncoder::new_lossless(&mut data))?,
+        OutputFormat::Pdf => {
+            return Err(PageShotError::InvalidOptions("Tiled and paginated captures require image output".to_string()));
+        }
+    }
+
+    Ok(data)
+}
+
+/// Split a page into images of `page_height` pixels each (the last one may be
+/// shorter), encoded in `format`
+pub(crate) fn paginate(page: &RgbaImage, page_height: u32, format: OutputFormat, quality: Option<u32>) -> Result<Vec<Vec<u8>>> {
+    (0..page.height())
+        .step_by(page_height as usize)
+        .map(|y| {
+            let slice = imageops::crop_imm(page, 0, y, page.width(), page_height.min(page.height() - y));
+            encode(&slice.to_image(), format, quality)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heights(pages: &[Vec<u8>]) -> Vec<u32> {
+        pages.iter().map(|page| image::load_from_memory(page).unwrap().height()).collect()
+    }
+
+    #[test]
+    fn paginate_keeps_a_shorter_last_page() {
+        let pages = paginate(&RgbaImage::new(10, 25), 10, OutputFormat::Png, None).unwrap();
+        assert_eq!(heights(&pages), [10, 10, 5]);
+    }
+
+    #[test]
+    fn paginate_exact_multiple() {
+        let pages = paginate(&RgbaImage::new(10, 20), 10, OutputFormat::Jpeg, Some(80)).unwrap();
+        assert_eq!(heights(&pages), [10, 10]);
+    }
+
+    #[test]
+    fn paginate_page_taller_than_image() {
+        let pages = paginate(&RgbaImage::new(10, 5), 10, OutputFormat::Webp, None).unwrap();
+        assert_eq!(heights(&pages), [5]);
+    }
+
+    #[test]
+    fn paginate_rejects_pdf() {
+        assert!(paginate(&RgbaImage::new(10, 5), 10, OutputFormat::Pdf, None).is_err());
+    }
+}
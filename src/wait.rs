@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+
+use headless_chrome::{
+    Tab,
+    protocol::cdp::Page,
+    protocol::cdp::types::Event,
+    protocol::cdp::Network,
+    protocol::cdp::Runtime,
+};
+
+use crate::error::{PageShotError, Result};
+use crate::options::WaitUntil;
+
+/// How long the network must stay quiet to count as idle
+const NETWORK_IDLE_TIME: Duration = Duration::from_millis(500);
+
+/// Main frame lifecycle and network state observed on a tab
+pub(crate) struct PageActivity {
+    dom_content_loaded: bool,
+    loaded: bool,
+    in_flight: HashSet<String>,
+    /// When the number of in-flight requests last dropped to zero
+    idle_since: Option<Instant>,
+    /// When the number of in-flight requests last dropped to two or fewer
+    almost_idle_since: Option<Instant>,
+    /// HTTP status of the main document, after redirects
+    pub(crate) document_status: Option<u32>,
+}
+
+impl PageActivity {
+    fn new() -> Self {
+        let now = Instant::now();
+
+        PageActivity {
+            dom_content_loaded: false,
+            loaded: false,
+            in_flight: HashSet::new(),
+            idle_since: Some(now),
+            almost_idle_since: Some(now),
+            document_status: None,
+        }
+    }
+
+    fn network_changed(&mut self) {
+        let now = Instant::now();
+        let count = self.in_flight.len();
+
+        self.idle_since = if count == 0 { self.idle_since.or(Some(now)) } else { None };
+        self.almost_idle_since = if count <= 2 { self.almost_idle_since.or(Some(now)) } else { None };
+    }
+
+    /// Whether `wait_until` holds, counting network quiet time from no earlier than `since`
+    fn is_ready(&self, wait_until: WaitUntil, since: Instant) -> bool {
+        let quiet = |quiet_since: Option<Instant>| {
+            quiet_since.is_some_and(|t| t.max(since).elapsed() >= NETWORK_IDLE_TIME)
+        };
+
+        match wait_until {
+            WaitUntil::Load => self.loaded,
+            WaitUntil::DomContentLoaded => self.dom_content_loaded,
+            WaitUntil::NetworkIdle0 => self.loaded && quiet(self.idle_since),
+            WaitUntil::NetworkIdle2 => self.loaded && quiet(self.almost_idle_since),
+        }
+    }
+}
+
+/// Start recording main frame lifecycle events, the document response and
+/// in-flight network requests on `tab`
+pub(crate) fn track_page_activity(tab: &Tab) -> Result<Arc<Mutex<PageActivity>>> {
+    let main_frame = tab.call_method(Page::GetFrameTree(None))?.frame_tree.frame.id;
+
+    tab.call_method(Network::Enable {
+        max_total_buffer_size: None,
+        max_resource_buffer_size: None,
+        max_post_data_size: None,
+        report_direct_socket_traffic: None,
+        enable_durable_messages: None,
+    })?;
+
+    let activity = Arc::new(Mutex::new(PageActivity::new()));
+    let state = Arc::clone(&activity);
+
+    tab.add_event_listener(Arc::new(move |event: &Event| {
+        let mut state = state.lock().unwrap();
+
+        match event {
+            Event::PageLifecycleEvent(event) if event.params.frame_id == main_frame => {
+                match event.params.name.as_str() {
+                    "init" => {
+                        state.dom_content_loaded = false;
+                        state.loaded = false;
+                    }
+                    "DOMContentLoaded" => state.dom_content_loaded = true,
+                    "load" => state.loaded = true,
+                    _ => {}
+                }
+            }
+            Event::NetworkResponseReceived(event)
+                if event.params.Type == Network::ResourceType::Document
+                    && event.params.frame_id.as_ref() == Some(&main_frame) =>
+            {
+                state.document_status = Some(event.params.response.status);
+            }
+            Event::NetworkRequestWillBeSent(event) => {
+                state.in_flight.insert(event.params.request_id.clone());
+                state.network_changed();
+            }
+            Event::NetworkLoadingFinished(event) => {
+                state.in_flight.remove(&event.params.request_id);
+                state.network_changed();
+            }
+            Event::NetworkLoadingFailed(event) => {
+                state.in_flight.remove(&event.params.request_id);
+                state.network_changed();
+            }
+            _ => {}
+        }
+    }))?;
+
+    Ok(activity)
+}
+
+/// Poll until `wait_until` holds for the tracked page, or fail with
+/// [`PageShotError::Timeout`] once `deadline` has passed.
+pub(crate) fn wait_for_activity(
+    activity: &Mutex<PageActivity>,
+    wait_until: WaitUntil,
+    since: Instant,
+    deadline: Instant,
+    timeout: Duration,
+) -> Result<()> {
+    loop {
+        if activity.lock().unwrap().is_ready(wait_until, since) {
+            return Ok(());
+        }
+
+        if Instant::now() >= deadline {
+            return Err(PageShotError::Timeout {
+                condition: format!("'{}'", wait_until.to_possible_value().unwrap().get_name()),
+                timeout,
+            });
+        }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Poll until an element matching `selector` exists and is visible, or fail
+/// with [`PageShotError::Timeout`] once `deadline` has passed.
+pub(crate) fn wait_for_visible(tab: &Tab, selector: &str, deadline: Instant, timeout: Duration) -> Result<()> {
+    loop {
+        // Lookup and evaluation errors (e.g. the context being replaced by a
+        // client-side redirect) count as "not visible yet"
+        if let Ok(element) = tab.find_element(selector) {
+            let visible = element.call_js_fn(
+                "function() {
+                    const style = window.getComputedStyle(this);
+                    const rect = this.getBoundingClientRect();
+                    return style.display !== 'none'
+                        && style.visibility !== 'hidden'
+                        && rect.width > 0
+                        && rect.height > 0;
+                }",
+                vec![],
+                false
+            )
+            .ok()
+            .and_then(|result| result.value)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+            if visible {
+                return Ok(());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return Err(PageShotError::Timeout {
+                condition: format!("selector '{}'", selector),
+                timeout,
+            });
+        }
+
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Poll `expression` every `interval` until it evaluates to a truthy value, or
+/// fail with [`PageShotError::Timeout`] once `deadline` has passed.
+///
+/// Exceptions thrown by the expression and failed evaluations count as "not
+/// ready yet", so predicates may reference globals that only exist after
+/// hydration and survive client-side navigations; the last exception is
+/// included in the timeout message. An expression that doesn't compile fails
+/// right away with [`PageShotError::Evaluation`].
+pub(crate) fn wait_for_function(
+    tab: &Tab,
+    expression: &str,
+    interval: Duration,
+    deadline: Instant,
+    timeout: Duration,
+) -> Result<()> {
+    // Exceptions are caught in the page, so only compile errors reach `exception_details`
+    let predicate = format!(
+        "(async () => {{
+            try {{
+                return {{ ready: !!(await ({})) }};
+            }} catch (error) {{
+                return {{ error: String(error) }};
+            }}
+        }})()",
+        expression
+    );
+
+    let mut last_error = None;
+
+    loop {
+        let evaluated = tab.call_method(Runtime::Evaluate {
+            expression: predicate.clone(),
+            return_by_value: Some(true),
+            generate_preview: None,
+            silent: Some(true),
+            await_promise: Some(true),
+            include_command_line_api: None,
+            user_gesture: None,
+            object_group: None,
+            context_id: None,
+            throw_on_side_effect: None,
+            timeout: None,
+            disable_breaks: None,
+            repl_mode: None,
+            allow_unsafe_eval_blocked_by_csp: None,
+            unique_context_id: None,
+            serialization_options: None,
+        });
+
+        // Protocol errors (e.g. the context being replaced mid-evaluation) count as "not ready yet"
+        if let Ok(evaluated) = evaluated {
+            if evaluated.exception_details.is_some() {
+                return Err(PageShotError::Evaluation {
+                    expression: expression.to_string(),
+                    expected: "valid JavaScript expression",
+                });
+            }
+
+            let value = evaluated.result.value.unwrap_or_default();
+
+            if value["ready"].as_bool() == Some(true) {
+                return Ok(());
+            }
+
+            if let Some(error) = value["error"].as_str() {
+                last_error = Some(error.to_string());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            let condition = match &last_error {
+                Some(error) => format!("function '{}' (last error: {})", expression, error),
+                None => format!("function '{}'", expression),
+            };
+
+            return Err(PageShotError::Timeout { condition, timeout });
+        }
+
+        std::thread::sleep(interval);
+    }
+}